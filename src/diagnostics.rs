@@ -0,0 +1,103 @@
+// Diagnostics: measures how long a noise generation pass takes against how
+// long its buffer actually takes to play out, so a DMA underrun -- which
+// otherwise fails silently -- shows up as a concrete signal over defmt/RTT.
+// This is the headroom a heavier filter cascade or stereo generation needs
+// to stay inside before it starts overrunning the real-time budget.
+use cortex_m::peripheral::{DCB, DWT};
+
+/// Enables the Cortex-M cycle counter (DWT CYCCNT) so `cycles()` advances.
+pub fn enable_cycle_counter(dcb: &mut DCB, dwt: &mut DWT) {
+    dcb.enable_trace();
+    dwt.enable_cycle_counter();
+}
+
+/// Current value of the free-running cycle counter.
+pub fn cycles() -> u32 {
+    DWT::cycle_count()
+}
+
+/// Tracks generation time versus playout budget across buffers, counts
+/// detected underruns, and periodically reports samples/sec and CPU-idle
+/// percentage over defmt.
+pub struct Diagnostics {
+    cpu_hz: u32,
+    playout_cycles_per_buffer: u32,
+    samples_per_buffer: u32,
+    underrun_count: u32,
+    buffers_since_report: u32,
+    busy_cycles_since_report: u64,
+    report_period_buffers: u32,
+    window_start_cycles: u32,
+}
+
+impl Diagnostics {
+    /// `cpu_hz` is the core clock generation runs at, `samples_per_buffer`
+    /// is one DMA buffer's sample count, and `sample_rate_hz` is the LRCK
+    /// rate at which that buffer is played out.
+    pub fn new(cpu_hz: u32, samples_per_buffer: u32, sample_rate_hz: u32) -> Self {
+        let playout_cycles_per_buffer =
+            ((cpu_hz as u64 * samples_per_buffer as u64) / sample_rate_hz as u64) as u32;
+        Self {
+            cpu_hz,
+            playout_cycles_per_buffer,
+            samples_per_buffer,
+            underrun_count: 0,
+            buffers_since_report: 0,
+            busy_cycles_since_report: 0,
+            // Report roughly once a second.
+            report_period_buffers: (sample_rate_hz / samples_per_buffer).max(1),
+            window_start_cycles: cycles(),
+        }
+    }
+
+    /// Records that the most recent buffer's generation took
+    /// `generation_cycles` cycles, flags an underrun if that exceeded the
+    /// buffer's playout duration, and periodically logs throughput and idle
+    /// time.
+    pub fn record_generation(&mut self, generation_cycles: u32) {
+        if generation_cycles > self.playout_cycles_per_buffer {
+            self.underrun_count += 1;
+            defmt::warn!(
+                "noise generation underrun: {=u32} cycles > {=u32} cycle budget ({=u32} underruns total)",
+                generation_cycles,
+                self.playout_cycles_per_buffer,
+                self.underrun_count
+            );
+        }
+
+        self.busy_cycles_since_report += generation_cycles as u64;
+        self.buffers_since_report += 1;
+
+        if self.buffers_since_report >= self.report_period_buffers {
+            // Measured against real wall-clock cycles elapsed since the last
+            // report, not the nominal configured rate, so a sustained
+            // underrun actually drags this number down.
+            let now = cycles();
+            let elapsed_cycles = now.wrapping_sub(self.window_start_cycles) as u64;
+            let samples_per_sec = if elapsed_cycles > 0 {
+                (self.samples_per_buffer as u64
+                    * self.buffers_since_report as u64
+                    * self.cpu_hz as u64)
+                    / elapsed_cycles
+            } else {
+                0
+            };
+
+            let budget_cycles =
+                self.playout_cycles_per_buffer as u64 * self.buffers_since_report as u64;
+            let busy_pct = ((self.busy_cycles_since_report * 100 / budget_cycles) as u32).min(100);
+            let idle_pct = 100 - busy_pct;
+
+            defmt::info!(
+                "noise diagnostics: {=u64} samples/sec, {=u32}% cpu idle, {=u32} underruns",
+                samples_per_sec,
+                idle_pct,
+                self.underrun_count
+            );
+
+            self.buffers_since_report = 0;
+            self.busy_cycles_since_report = 0;
+            self.window_start_cycles = now;
+        }
+    }
+}
@@ -9,11 +9,14 @@ use hal::pac;
 use hal::pio::PIOExt;
 use hal::pio::ShiftDirection;
 use hal::Sio;
+use noise_generator::{gen_white_noise, spectrum};
 use panic_halt as _;
 use rand::rngs::SmallRng;
-use rand::{Rng, SeedableRng};
+use rand::SeedableRng;
 use rp2040_hal as hal;
 
+mod diagnostics;
+
 /// The linker will place this boot block at the start of our program image. We
 /// need this to help the ROM bootloader get our code up and running.
 /// Note: This boot block is not necessary when using a rp-hal based BSP
@@ -77,7 +80,30 @@ fn generate_sawtooth_wave(samples: &mut [u32]) {
     }
 }
 
-// Generates brown noise in the low 16 bits (mono) of each buffer sample.
+// Advances one channel's brown noise random walk by a single sample, given
+// an independently-drawn white noise value, and returns the new sample.
+fn brown_step(prior_sample: I16F16, white: I16F16) -> I16F16 {
+    const LEAKAGE: I16F16 = I16F16::lit("0.997");
+    const SCALING: I16F16 = I16F16::lit("0.01");
+    const VOLUME: I16F16 = I16F16::lit("0.25");
+
+    let maybe_new_sample = LEAKAGE * prior_sample + white * SCALING;
+
+    // Brown noise random walk can overflow, so here we invert the random walk if we would otherwise
+    // overflow [-0.25, 0.25].
+    let new_sample = if maybe_new_sample >= 0.25 || maybe_new_sample <= -0.25 {
+        LEAKAGE * prior_sample - white * SCALING
+    } else {
+        maybe_new_sample
+    };
+    new_sample * VOLUME
+}
+
+// Generates stereo brown noise, packing the left channel into bits 31:16 and
+// the right channel into bits 15:0 of each buffer sample, matching the PIO's
+// `| sample ws=0 | sample ws=1 |` FIFO word format.
+// Each channel is driven by its own random walk state so the left and right
+// channels are decorrelated rather than duplicates of one mono stream.
 // Depends upon the last sample of the prior buuffer for smoothing.
 // https://github.com/audacity/audacity/blob/236b188d6bba08ff902a7095c0425fd4a7e743de/src/effects/Noise.cpp#L141
 // We use I16F16 to represent samples to ease the converstions from the RNG.
@@ -85,46 +111,68 @@ fn generate_sawtooth_wave(samples: &mut [u32]) {
 fn generate_brown_noise(
     rng: &mut SmallRng,
     gen_num: usize,
-    prior_sample: I16F16,
+    prior_samples: (I16F16, I16F16),
     samples: &mut [u32],
-) -> I16F16 {
-    const LEAKAGE: I16F16 = I16F16::lit("0.997");
-    const SCALING: I16F16 = I16F16::lit("0.01");
-    const VOLUME: I16F16 = I16F16::lit("0.25");
-    let mut prior_sample = prior_sample;
+) -> (I16F16, I16F16) {
+    let (mut prior_left, mut prior_right) = prior_samples;
     for sample in samples.iter_mut() {
-        // Generate a white noise sample value in range [-1.0, 1.0] in I16F16
-        let rv = rng.gen::<u32>();
-        // If the high bit is one, represent as a negative value.
-        let white: I16F16 = if rv & 0x8000_0000 == 0 {
-            I16F16::from_bits((rv & 0x0000_FFFF) as i32)
-        } else {
-            I16F16::from_bits((rv & 0x0000_FFFF) as i32) * -1
-        };
+        let left = brown_step(prior_left, gen_white_noise(rng));
+        let right = brown_step(prior_right, gen_white_noise(rng));
 
-        let maybe_new_sample = LEAKAGE * prior_sample + white * SCALING;
+        // Scale the I16F16 so that it's integral part can be used in an i16
+        let left_bits = (left * I16F16::MAX).to_num::<i16>() as u32 & 0xFFFF;
+        let right_bits = (right * I16F16::MAX).to_num::<i16>() as u32 & 0xFFFF;
+        *sample = (left_bits << 16) | right_bits;
 
-        // Brown noise random walk can overflow, so here we invert the random walk if we would otherwise
-        // overflow [-0.25, 0.25].
-        let new_sample = if maybe_new_sample >= 0.25 || maybe_new_sample <= -0.25 {
-            LEAKAGE * prior_sample - white * SCALING
-        } else {
-            maybe_new_sample
-        };
-        let new_sample = new_sample * VOLUME;
+        prior_left = left;
+        prior_right = right;
+    }
+    return (prior_left, prior_right);
+}
 
-        // Scale the I16F16 so that it's integral part can be used in an i16
-        let scaled_sample = new_sample * I16F16::MAX;
-        *sample = (scaled_sample.to_num::<i16>()) as u32 & 0xFFFF;
-        prior_sample = new_sample;
+// Recovers a channel's normalized I16F16 sample from the packed FIFO word
+// written by `generate_brown_noise`, so the spectral self-test can run on
+// real generated output instead of a separately-synthesized signal.
+fn extract_left_channel(word: u32) -> I16F16 {
+    I16F16::from_num((word >> 16) as u16 as i16) / I16F16::MAX
+}
+
+// Feeds generated samples into a rolling spectrum::BLOCK_SIZE block and, once
+// full, runs the spectral self-test and logs octave-spaced magnitude bins
+// over defmt/RTT so the noise color can be confirmed on-device instead of
+// via offline WAV inspection.
+fn feed_spectrum_block(
+    block: &mut [I16F16; spectrum::BLOCK_SIZE],
+    fill: &mut usize,
+    samples: &[u32],
+) {
+    for &word in samples {
+        block[*fill] = extract_left_channel(word);
+        *fill += 1;
+
+        if *fill == spectrum::BLOCK_SIZE {
+            let magnitudes = spectrum::analyze(block);
+            defmt::info!(
+                "noise spectrum bins [1,2,4,8,16,32,64]: {=f32} {=f32} {=f32} {=f32} {=f32} {=f32} {=f32}",
+                magnitudes[1].to_num::<f32>(),
+                magnitudes[2].to_num::<f32>(),
+                magnitudes[4].to_num::<f32>(),
+                magnitudes[8].to_num::<f32>(),
+                magnitudes[16].to_num::<f32>(),
+                magnitudes[32].to_num::<f32>(),
+                magnitudes[64].to_num::<f32>(),
+            );
+            *fill = 0;
+        }
     }
-    return prior_sample;
 }
 
 // Entry point to our bare-metal application.
 #[rp2040_hal::entry]
 fn main() -> ! {
     let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = cortex_m::Peripherals::take().unwrap();
+    diagnostics::enable_cycle_counter(&mut core.DCB, &mut core.DWT);
 
     let sio = Sio::new(pac.SIO);
     let pins = hal::gpio::Pins::new(
@@ -194,7 +242,7 @@ fn main() -> ! {
     // All frequencies are pulled from Table 11. BCK Rates (MHz) by LRCK Sample Rate for PCM510xA PLL Operation
     // From the "PCM510xA 2.1 VRMS, 112/106/100 dB Audio Stereo DAC with PLL and 32-bit, 384 kHz PCM Interface" data sheet
     // We are going to use a BCK frequency at 64 times the lrck signal. The PCM5100A will accept 32 or 64 times the sampling rate.
-    let (_lrck_freq, bck_freq): (f32, f32) = {
+    let (lrck_freq, bck_freq): (f32, f32) = {
         match target_lrck_freq {
             SampleFrequency::Freq32khz => (32_000f32, 1.024E06_f32),
             SampleFrequency::Freq44_1khz => (44_100f32, 1.4112E06_f32),
@@ -259,11 +307,15 @@ fn main() -> ! {
     let tx_buf1 = singleton!(: [u32; TABLE_SIZE] = message1).unwrap();
     let tx_buf2 = singleton!(: [u32; TABLE_SIZE] = message2).unwrap();
     let mut small_rng = SmallRng::seed_from_u64(0xfeedbeeffeedbeef_u64);
-    let mut prior_sample = I16F16::lit("0.0");
+    let mut prior_samples = (I16F16::lit("0.0"), I16F16::lit("0.0"));
     let mut gen_num: usize = 0;
-    prior_sample = generate_brown_noise(&mut small_rng, gen_num, prior_sample, tx_buf1);
+    let mut diag =
+        diagnostics::Diagnostics::new(BASE_CLOCK as u32, TABLE_SIZE as u32, lrck_freq as u32);
+    let mut spectrum_block = [I16F16::from_num(0); spectrum::BLOCK_SIZE];
+    let mut spectrum_fill: usize = 0;
+    prior_samples = generate_brown_noise(&mut small_rng, gen_num, prior_samples, tx_buf1);
     gen_num += 1;
-    prior_sample = generate_brown_noise(&mut small_rng, gen_num, prior_sample, tx_buf2);
+    prior_samples = generate_brown_noise(&mut small_rng, gen_num, prior_samples, tx_buf2);
     gen_num += 1;
     let tx_transfer1 = single_buffer::Config::new(dma.ch0, tx_buf1, tx).start();
     let (ch0, tx_buf1, tx) = tx_transfer1.wait();
@@ -281,8 +333,11 @@ fn main() -> ! {
         if tx_transfer.is_done() {
             // Here we generate new brown noise while the last DMA (triggered by read_next below)
             // is still doing its thing.
-            prior_sample = generate_brown_noise(&mut small_rng, gen_num, prior_sample, next_buf);
+            let generation_start = diagnostics::cycles();
+            prior_samples = generate_brown_noise(&mut small_rng, gen_num, prior_samples, next_buf);
             gen_num += 1;
+            feed_spectrum_block(&mut spectrum_block, &mut spectrum_fill, next_buf);
+            diag.record_generation(diagnostics::cycles().wrapping_sub(generation_start));
             // wait is a blocking call, returns when tx_transfer is complete
             let (tx_buf, next_tx_transfer) = tx_transfer.wait();
             // read_next is IMO confusing named - but from our point of view it's toggling
@@ -0,0 +1,97 @@
+// On-device spectral self-test: runs an FFT over a block of generated noise
+// so the firmware can log the spectral slope (flat for white, -3 dB/octave
+// for pink, -6 dB/octave for brown) over defmt/RTT instead of relying on
+// offline WAV inspection.
+use core::f32::consts::PI;
+use fixed::types::I16F16;
+use libm::{cosf, sqrtf};
+use microfft::real::rfft_256;
+
+/// Number of samples analyzed per spectral snapshot.
+pub const BLOCK_SIZE: usize = 256;
+/// Number of magnitude bins returned (microfft's real FFT folds the
+/// conjugate-symmetric upper half of the spectrum into the first N/2 bins).
+pub const BIN_COUNT: usize = BLOCK_SIZE / 2;
+
+/// Applies a Hann window to `block` and runs an in-place 256-point real FFT,
+/// returning the per-bin magnitude.
+pub fn analyze(block: &[I16F16; BLOCK_SIZE]) -> [I16F16; BIN_COUNT] {
+    let mut windowed = [0f32; BLOCK_SIZE];
+    for (n, sample) in block.iter().enumerate() {
+        windowed[n] = sample.to_num::<f32>() * hann(n);
+    }
+
+    let spectrum = rfft_256(&mut windowed);
+
+    // microfft packs the real-valued Nyquist coefficient into the imaginary
+    // part of bin 0 (to return N/2 complex values instead of N/2+1), so bin 0
+    // is not a plain complex magnitude: its real part is DC, its imaginary
+    // part is Nyquist. We only report DC here and drop Nyquist, since none of
+    // the octave-spaced bins this module's caller logs are anywhere near it.
+    let mut magnitudes = [I16F16::from_num(0); BIN_COUNT];
+    magnitudes[0] = I16F16::from_num(spectrum[0].re.abs());
+    for (bin, c) in spectrum.iter().enumerate().skip(1) {
+        magnitudes[bin] = I16F16::from_num(sqrtf(c.re * c.re + c.im * c.im));
+    }
+    magnitudes
+}
+
+// Hann window: w[n] = 0.5 * (1 - cos(2*pi*n / (N-1))). Tapers the block's
+// edges to reduce the spectral leakage a hard rectangular window would add.
+fn hann(n: usize) -> f32 {
+    0.5 * (1.0 - cosf(2.0 * PI * n as f32 / (BLOCK_SIZE as f32 - 1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A silent block should carry no energy in any bin.
+    #[test]
+    fn analyze_silence_is_all_zero() {
+        let block = [I16F16::from_num(0); BLOCK_SIZE];
+        let magnitudes = analyze(&block);
+        for m in magnitudes.iter() {
+            assert_eq!(*m, I16F16::from_num(0));
+        }
+    }
+
+    // A pure tone at bin `k` should concentrate its energy there, well above
+    // the leakage the Hann window spreads into neighboring bins.
+    #[test]
+    fn analyze_pure_tone_peaks_at_its_bin() {
+        const BIN: usize = 20;
+        let mut block = [I16F16::from_num(0); BLOCK_SIZE];
+        for (n, sample) in block.iter_mut().enumerate() {
+            let phase = 2.0 * PI * BIN as f32 * n as f32 / BLOCK_SIZE as f32;
+            *sample = I16F16::from_num(cosf(phase));
+        }
+
+        let magnitudes = analyze(&block);
+        let peak = magnitudes[BIN].to_num::<f32>();
+        for (bin, m) in magnitudes.iter().enumerate() {
+            if bin != BIN {
+                assert!(m.to_num::<f32>() < peak);
+            }
+        }
+    }
+
+    // A pure Nyquist-frequency tone (alternating +1/-1) carries essentially no
+    // DC energy, so bin 0 should report a small magnitude. If bin 0 instead
+    // blended in microfft's packed Nyquist coefficient, it would report the
+    // large magnitude of the tone itself.
+    #[test]
+    fn analyze_bin_zero_reports_dc_not_nyquist() {
+        let mut block = [I16F16::from_num(0); BLOCK_SIZE];
+        for (n, sample) in block.iter_mut().enumerate() {
+            *sample = if n % 2 == 0 {
+                I16F16::from_num(1)
+            } else {
+                I16F16::from_num(-1)
+            };
+        }
+
+        let magnitudes = analyze(&block);
+        assert!(magnitudes[0].to_num::<f32>() < 5.0);
+    }
+}